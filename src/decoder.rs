@@ -0,0 +1,199 @@
+//! The keystroke-to-barcode decoding state machine.
+//!
+//! This is shared between [`crate::BarcodeScanner::read`], which drives it from
+//! blocking `fetch_events()` batches, and the `tokio`+`futures` stream built on
+//! evdev's own async `EventStream`, which drives it one event at a time.
+
+use std::time::Duration;
+
+use crate::layout::Modifier;
+use crate::{Error, Framing, Layout};
+
+/// The `SYN_DROPPED` code from `linux/input-event-codes.h`, signalling that the
+/// kernel's evdev event queue overflowed and some events were lost.
+pub(crate) const SYN_DROPPED: u16 = 3;
+
+/// The state of the Shift, AltGr and Caps Lock modifiers.
+///
+/// Shift and AltGr are tracked as held; Caps Lock is tracked as a toggle, since
+/// the key itself is momentary but its effect on the keyboard is not.
+#[derive(Debug, Clone, Copy, Default)]
+struct ModifierState {
+	left_shift: bool,
+	right_shift: bool,
+	right_alt: bool,
+	capslock: bool,
+}
+
+impl ModifierState {
+	/// Which [`Modifier`] level of the active [`Layout`] applies right now.
+	fn active(&self) -> Modifier {
+		if self.right_alt {
+			Modifier::AltGr
+		} else if self.left_shift || self.right_shift || self.capslock {
+			Modifier::Shift
+		} else {
+			Modifier::Base
+		}
+	}
+}
+
+/// Turns a stream of `evdev` key events into complete barcodes.
+pub(crate) struct Decoder {
+	/// A buffer used to collect keystrokes in until a whole barcode has been read.
+	buffer: String,
+
+	/// The state of the Shift, AltGr and Caps Lock modifiers, persisted across
+	/// event batches.
+	modifiers: ModifierState,
+
+	/// The keyboard layout used to decode key events into characters.
+	layout: Layout,
+
+	/// How to split the buffer into complete barcodes.
+	framing: Framing,
+}
+
+impl Decoder {
+	pub(crate) fn new(layout: Layout) -> Self {
+		Self {
+			buffer: String::new(),
+			modifiers: ModifierState::default(),
+			layout,
+			framing: Framing::default(),
+		}
+	}
+
+	pub(crate) fn layout(&mut self) -> &mut Layout {
+		&mut self.layout
+	}
+
+	pub(crate) fn framing(&mut self) -> &mut Framing {
+		&mut self.framing
+	}
+
+	/// The idle timeout configured on this decoder's [`Framing`], if any.
+	pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+		self.framing.idle_timeout()
+	}
+
+	/// Feed one `evdev` key event into the decoder.
+	///
+	/// The caller is expected to check [`Decoder::is_sync_dropped`] first and
+	/// call [`Decoder::recover_from_sync_drop`] instead of this method for a
+	/// `SYN_DROPPED` event.
+	///
+	/// Returns `Some(barcode)` once a full barcode has been read. Returns `None`
+	/// while the barcode is still being assembled.
+	pub(crate) fn feed_event(&mut self, event: evdev::InputEvent) -> Option<String> {
+		// Check if key is pressed (value 1 for the key pressed, velue 0 for the key released).
+		if event.event_type() == evdev::EventType::KEY {
+			// Create Key object based on the code.
+			let key_name = evdev::Key(event.code());
+
+			// Modifier state lives on the decoder so it survives across batches
+			// instead of resetting every call; Caps Lock is a toggle, not a held key.
+			match key_name {
+				evdev::Key::KEY_LEFTSHIFT => self.modifiers.left_shift = event.value() == 1,
+				evdev::Key::KEY_RIGHTSHIFT => self.modifiers.right_shift = event.value() == 1,
+				evdev::Key::KEY_RIGHTALT => self.modifiers.right_alt = event.value() == 1,
+				evdev::Key::KEY_CAPSLOCK if event.value() == 1 => {
+					self.modifiers.capslock = !self.modifiers.capslock;
+				},
+				_ => {},
+			}
+
+			// Map key_name to the number or char using the active layout.
+			if event.value() == 1 {
+				if let Some(c) = self.layout.lookup(key_name, self.modifiers.active()) {
+					self.buffer.push(c);
+				}
+			}
+		}
+
+		self.framing.split(&mut self.buffer)
+	}
+
+	/// Flush a barcode still sitting in the buffer with no terminator seen,
+	/// per [`Framing::with_idle_timeout`]. Returns `None` if the buffer is
+	/// empty, i.e. nothing was lost by the caller waiting this long.
+	pub(crate) fn flush_idle(&mut self) -> Option<String> {
+		self.framing.flush(&mut self.buffer)
+	}
+
+	/// Discard a partially-read barcode after a `SYN_DROPPED`, and rebuild
+	/// modifier state from the device's actual current key and LED state
+	/// rather than trusting whatever was tracked before the drop.
+	///
+	/// Returns an [`Error`] when a partial scan was discarded, so the caller
+	/// can tell a dropped scan apart from an ordinary empty read; returns
+	/// `None` when nothing was lost.
+	pub(crate) fn recover_from_sync_drop(&mut self, device: &evdev::Device) -> Option<Error> {
+		let had_partial_scan = !self.buffer.is_empty();
+		self.buffer.clear();
+
+		if let Ok(key_state) = device.get_key_state() {
+			self.modifiers.left_shift = key_state.contains(evdev::Key::KEY_LEFTSHIFT);
+			self.modifiers.right_shift = key_state.contains(evdev::Key::KEY_RIGHTSHIFT);
+			self.modifiers.right_alt = key_state.contains(evdev::Key::KEY_RIGHTALT);
+		}
+		if let Ok(led_state) = device.get_led_state() {
+			self.modifiers.capslock = led_state.contains(evdev::LedType::LED_CAPSL);
+		}
+
+		had_partial_scan.then(|| Error::sync_dropped(
+			"Input device's event buffer overflowed mid-scan (SYN_DROPPED); discarded a partial barcode".to_string()
+		))
+	}
+
+	/// Whether `event` was a `SYN_DROPPED` that the caller should react to by
+	/// calling [`Decoder::recover_from_sync_drop`].
+	pub(crate) fn is_sync_dropped(event: &evdev::InputEvent) -> bool {
+		event.event_type() == evdev::EventType::SYNCHRONIZATION && event.code() == SYN_DROPPED
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn key_event(key: evdev::Key, value: i32) -> evdev::InputEvent {
+		evdev::InputEvent::new(evdev::EventType::KEY, key.0, value)
+	}
+
+	#[test]
+	fn assembles_a_barcode_up_to_the_terminator() {
+		let mut decoder = Decoder::new(Layout::us());
+		assert_eq!(decoder.feed_event(key_event(evdev::Key::KEY_A, 1)), None);
+		assert_eq!(decoder.feed_event(key_event(evdev::Key::KEY_1, 1)), None);
+		assert_eq!(decoder.feed_event(key_event(evdev::Key::KEY_ENTER, 1)), Some("a1".to_string()));
+	}
+
+	#[test]
+	fn shift_selects_the_upper_level() {
+		let mut decoder = Decoder::new(Layout::us());
+		decoder.feed_event(key_event(evdev::Key::KEY_LEFTSHIFT, 1));
+		decoder.feed_event(key_event(evdev::Key::KEY_A, 1));
+		decoder.feed_event(key_event(evdev::Key::KEY_LEFTSHIFT, 0));
+		let barcode = decoder.feed_event(key_event(evdev::Key::KEY_ENTER, 1));
+		assert_eq!(barcode, Some("A".to_string()));
+	}
+
+	#[test]
+	fn capslock_toggles_independent_of_held_state() {
+		let mut decoder = Decoder::new(Layout::us());
+		decoder.feed_event(key_event(evdev::Key::KEY_CAPSLOCK, 1));
+		decoder.feed_event(key_event(evdev::Key::KEY_CAPSLOCK, 0));
+		decoder.feed_event(key_event(evdev::Key::KEY_A, 1));
+		let barcode = decoder.feed_event(key_event(evdev::Key::KEY_ENTER, 1));
+		assert_eq!(barcode, Some("A".to_string()));
+	}
+
+	#[test]
+	fn is_sync_dropped_only_matches_the_syn_dropped_code() {
+		let dropped = evdev::InputEvent::new(evdev::EventType::SYNCHRONIZATION, SYN_DROPPED, 0);
+		let report = evdev::InputEvent::new(evdev::EventType::SYNCHRONIZATION, 0, 0);
+		assert!(Decoder::is_sync_dropped(&dropped));
+		assert!(!Decoder::is_sync_dropped(&report));
+	}
+}
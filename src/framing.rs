@@ -0,0 +1,188 @@
+//! Configurable barcode framing: terminators, prefix/suffix stripping, and
+//! idle-timeout flushing.
+//!
+//! [`BarcodeScanner::read`](crate::BarcodeScanner::read) originally
+//! hardcoded `\n` as the only terminator and popped exactly one trailing
+//! character. Many scanners are programmed to send `\r`, `\t`, a CR+LF pair,
+//! a symbology prefix, or no terminator at all, so this module factors that
+//! out into a [`Framing`] config consulted by the decoder instead.
+
+use std::time::Duration;
+
+/// One way a barcode's end can be recognized in the decoded character stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Terminator {
+	/// Carriage return (`\r`).
+	Cr,
+	/// Line feed (`\n`), the crate's original hardcoded terminator.
+	Lf,
+	/// Tab (`\t`).
+	Tab,
+	/// Any other fixed string a scanner is programmed to send.
+	Custom(String),
+}
+
+impl Terminator {
+	fn as_str(&self) -> &str {
+		match self {
+			Terminator::Cr => "\r",
+			Terminator::Lf => "\n",
+			Terminator::Tab => "\t",
+			Terminator::Custom(s) => s,
+		}
+	}
+}
+
+/// How a [`crate::BarcodeScanner`] splits the raw keystroke buffer into
+/// complete barcodes.
+///
+/// Configure with [`crate::BarcodeScanner::with_framing`]. The default
+/// matches the crate's original hardcoded behavior: `\n` only, no
+/// prefix/suffix stripping, no idle timeout.
+///
+/// # Example
+/// ```
+/// use barcode_scanner::{Framing, Terminator};
+/// use std::time::Duration;
+///
+/// let framing = Framing::default()
+///     .with_terminator(Terminator::Cr)
+///     .with_prefix("]C1")
+///     .with_idle_timeout(Duration::from_millis(50));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Framing {
+	terminators: Vec<Terminator>,
+	prefix: Option<String>,
+	suffix: Option<String>,
+	idle_timeout: Option<Duration>,
+}
+
+impl Framing {
+	/// Accept `terminator` as ending a barcode, in addition to any already configured.
+	pub fn with_terminator(mut self, terminator: Terminator) -> Self {
+		self.terminators.push(terminator);
+		self
+	}
+
+	/// Strip `prefix` (e.g. an AIM symbology identifier) from the front of every barcode.
+	pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+		self.prefix = Some(prefix.into());
+		self
+	}
+
+	/// Strip `suffix` from the end of every barcode, after the terminator is removed.
+	pub fn with_suffix(mut self, suffix: impl Into<String>) -> Self {
+		self.suffix = Some(suffix.into());
+		self
+	}
+
+	/// Flush the buffer as a complete barcode if no new key arrives within
+	/// `timeout`, for scanners programmed to send no terminator at all.
+	pub fn with_idle_timeout(mut self, timeout: Duration) -> Self {
+		self.idle_timeout = Some(timeout);
+		self
+	}
+
+	pub(crate) fn idle_timeout(&self) -> Option<Duration> {
+		self.idle_timeout
+	}
+
+	/// Find the earliest configured terminator in `buffer` and split a
+	/// completed barcode off the front, stripping the configured prefix and
+	/// suffix.
+	///
+	/// Normalizes a CR+LF pair into a single split: if the terminator that
+	/// matched is CR or LF and the other half immediately follows, it is
+	/// swallowed too, so one scan isn't read as a barcode followed by an
+	/// empty one.
+	pub(crate) fn split(&self, buffer: &mut String) -> Option<String> {
+		let (index, terminator) = self.terminators.iter()
+			.filter_map(|terminator| buffer.find(terminator.as_str()).map(|index| (index, terminator)))
+			.min_by_key(|&(index, _)| index)?;
+
+		let is_cr = *terminator == Terminator::Cr;
+		let is_lf = *terminator == Terminator::Lf;
+		let term_len = terminator.as_str().len();
+
+		let mut barcode: String = buffer.drain(..index + term_len).collect();
+		barcode.truncate(index);
+
+		if (is_cr && buffer.starts_with('\n')) || (is_lf && buffer.starts_with('\r')) {
+			buffer.remove(0);
+		}
+
+		Some(self.strip_affixes(barcode))
+	}
+
+	/// Take whatever is in `buffer`, if anything, as a complete barcode with
+	/// no terminator seen. Used by the idle timeout.
+	pub(crate) fn flush(&self, buffer: &mut String) -> Option<String> {
+		if buffer.is_empty() {
+			return None;
+		}
+		Some(self.strip_affixes(std::mem::take(buffer)))
+	}
+
+	fn strip_affixes(&self, mut barcode: String) -> String {
+		if let Some(prefix) = &self.prefix {
+			if let Some(stripped) = barcode.strip_prefix(prefix.as_str()) {
+				barcode = stripped.to_string();
+			}
+		}
+		if let Some(suffix) = &self.suffix {
+			if let Some(stripped) = barcode.strip_suffix(suffix.as_str()) {
+				barcode = stripped.to_string();
+			}
+		}
+		barcode
+	}
+}
+
+impl Default for Framing {
+	fn default() -> Self {
+		Self {
+			terminators: vec![Terminator::Lf],
+			prefix: None,
+			suffix: None,
+			idle_timeout: None,
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn splits_on_the_default_terminator() {
+		let framing = Framing::default();
+		let mut buffer = "12345\n".to_string();
+		assert_eq!(framing.split(&mut buffer), Some("12345".to_string()));
+		assert_eq!(buffer, "");
+	}
+
+	#[test]
+	fn normalizes_a_crlf_pair_into_a_single_split() {
+		let framing = Framing::default().with_terminator(Terminator::Cr);
+		let mut buffer = "12345\r\n67890\r\n".to_string();
+		assert_eq!(framing.split(&mut buffer), Some("12345".to_string()));
+		assert_eq!(framing.split(&mut buffer), Some("67890".to_string()));
+		assert_eq!(buffer, "");
+	}
+
+	#[test]
+	fn strips_configured_prefix_and_suffix() {
+		let framing = Framing::default().with_prefix("]C1").with_suffix("END");
+		let mut buffer = "]C112345END\n".to_string();
+		assert_eq!(framing.split(&mut buffer), Some("12345".to_string()));
+	}
+
+	#[test]
+	fn flush_takes_whatever_is_buffered_with_no_terminator_seen() {
+		let framing = Framing::default();
+		let mut buffer = "12345".to_string();
+		assert_eq!(framing.flush(&mut buffer), Some("12345".to_string()));
+		assert_eq!(framing.flush(&mut buffer), None);
+	}
+}
@@ -0,0 +1,206 @@
+//! Configurable keyboard layouts.
+//!
+//! `key_to_str`'s original table assumed the scanner emulates a US QWERTY
+//! keyboard. Scanners configured for other emulated layouts send the same
+//! `evdev` key codes for physically different characters, so decoding needs a
+//! layout to consult instead of a fixed table. See [`Layout::us`] for the
+//! built-in default and [`Layout::from_table`] for loading a custom one.
+
+use std::collections::HashMap;
+
+use crate::Error;
+
+/// Which shift level of a [`Layout`] an incoming key event should be read from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Modifier {
+	/// No modifier held.
+	Base,
+	/// Left or right Shift held, or Caps Lock toggled on.
+	Shift,
+	/// AltGr (`KEY_RIGHTALT`) held, for third-level symbols such as `@`, `{`, `}`
+	/// on German layouts.
+	AltGr,
+}
+
+/// One key's mapping across the three shift levels a [`Layout`] supports.
+///
+/// Deserializable from a simple table, e.g.
+/// ```json
+/// { "KEY_Y": { "unshifted": "z", "shifted": "Z" } }
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize))]
+pub struct LayoutEntry {
+	/// The character produced with no modifier held.
+	pub unshifted: Option<char>,
+
+	/// The character produced with Shift held or Caps Lock toggled on.
+	/// Falls back to `unshifted` when not set.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub shifted: Option<char>,
+
+	/// The character produced with AltGr held.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub altgr: Option<char>,
+}
+
+/// A keyboard layout: maps an `evdev` key plus a shift level to a character.
+///
+/// Build one from a table keyed by `evdev` key name (e.g. `"KEY_A"`, `"KEY_Y"`)
+/// with [`Layout::from_table`], or use the built-in [`Layout::us`].
+#[derive(Debug, Clone)]
+pub struct Layout {
+	entries: HashMap<evdev::Key, LayoutEntry>,
+}
+
+impl Layout {
+	/// The standard US QWERTY layout, matching this crate's original hardcoded
+	/// behavior. This is the default layout for a scanner that isn't built with
+	/// [`crate::BarcodeScanner::with_layout`].
+	pub fn us() -> Self {
+		let mut entries = HashMap::with_capacity(US_QWERTY.len());
+		for &(_name, key, unshifted, shifted) in US_QWERTY {
+			entries.insert(key, LayoutEntry {
+				unshifted: Some(unshifted),
+				shifted: Some(shifted),
+				altgr: None,
+			});
+		}
+		Self { entries }
+	}
+
+	/// Build a layout from a table of `evdev` key names (e.g. `"KEY_A"`,
+	/// `"KEY_102ND"`) to per-level characters. Any key `evdev::Key` recognizes
+	/// can be used, not just the ones [`Layout::us`] maps.
+	///
+	/// Returns an [`Error`] if the table references a key name `evdev` doesn't
+	/// recognize.
+	pub fn from_table(table: HashMap<String, LayoutEntry>) -> Result<Self, Error> {
+		let mut entries = HashMap::with_capacity(table.len());
+		for (name, entry) in table {
+			let key = name.parse::<evdev::Key>()
+				.map_err(|_| Error::new(format!("Unknown key name in layout table: {name}")))?;
+			entries.insert(key, entry);
+		}
+		Ok(Self { entries })
+	}
+
+	/// Look up the character a key produces under the given modifier, if any.
+	pub(crate) fn lookup(&self, key: evdev::Key, modifier: Modifier) -> Option<char> {
+		let entry = self.entries.get(&key)?;
+		match modifier {
+			Modifier::Base => entry.unshifted,
+			Modifier::Shift => entry.shifted.or(entry.unshifted),
+			Modifier::AltGr => entry.altgr,
+		}
+	}
+}
+
+impl Default for Layout {
+	fn default() -> Self {
+		Self::us()
+	}
+}
+
+/// `(key name, key, unshifted char, shifted char)`, the same table
+/// `key_to_str` used to hardcode.
+const US_QWERTY: &[(&str, evdev::Key, char, char)] = &[
+	// Digits
+	("KEY_1", evdev::Key::KEY_1, '1', '!'),
+	("KEY_2", evdev::Key::KEY_2, '2', '@'),
+	("KEY_3", evdev::Key::KEY_3, '3', '#'),
+	("KEY_4", evdev::Key::KEY_4, '4', '$'),
+	("KEY_5", evdev::Key::KEY_5, '5', '%'),
+	("KEY_6", evdev::Key::KEY_6, '6', '^'),
+	("KEY_7", evdev::Key::KEY_7, '7', '&'),
+	("KEY_8", evdev::Key::KEY_8, '8', '*'),
+	("KEY_9", evdev::Key::KEY_9, '9', '('),
+	("KEY_0", evdev::Key::KEY_0, '0', ')'),
+	// Letters
+	("KEY_A", evdev::Key::KEY_A, 'a', 'A'),
+	("KEY_B", evdev::Key::KEY_B, 'b', 'B'),
+	("KEY_C", evdev::Key::KEY_C, 'c', 'C'),
+	("KEY_D", evdev::Key::KEY_D, 'd', 'D'),
+	("KEY_E", evdev::Key::KEY_E, 'e', 'E'),
+	("KEY_F", evdev::Key::KEY_F, 'f', 'F'),
+	("KEY_G", evdev::Key::KEY_G, 'g', 'G'),
+	("KEY_H", evdev::Key::KEY_H, 'h', 'H'),
+	("KEY_I", evdev::Key::KEY_I, 'i', 'I'),
+	("KEY_J", evdev::Key::KEY_J, 'j', 'J'),
+	("KEY_K", evdev::Key::KEY_K, 'k', 'K'),
+	("KEY_L", evdev::Key::KEY_L, 'l', 'L'),
+	("KEY_M", evdev::Key::KEY_M, 'm', 'M'),
+	("KEY_N", evdev::Key::KEY_N, 'n', 'N'),
+	("KEY_O", evdev::Key::KEY_O, 'o', 'O'),
+	("KEY_P", evdev::Key::KEY_P, 'p', 'P'),
+	("KEY_Q", evdev::Key::KEY_Q, 'q', 'Q'),
+	("KEY_R", evdev::Key::KEY_R, 'r', 'R'),
+	("KEY_S", evdev::Key::KEY_S, 's', 'S'),
+	("KEY_T", evdev::Key::KEY_T, 't', 'T'),
+	("KEY_U", evdev::Key::KEY_U, 'u', 'U'),
+	("KEY_V", evdev::Key::KEY_V, 'v', 'V'),
+	("KEY_W", evdev::Key::KEY_W, 'w', 'W'),
+	("KEY_X", evdev::Key::KEY_X, 'x', 'X'),
+	("KEY_Y", evdev::Key::KEY_Y, 'y', 'Y'),
+	("KEY_Z", evdev::Key::KEY_Z, 'z', 'Z'),
+	// Special
+	("KEY_SPACE", evdev::Key::KEY_SPACE, ' ', ' '),
+	("KEY_TAB", evdev::Key::KEY_TAB, '\t', '\t'),
+	("KEY_APOSTROPHE", evdev::Key::KEY_APOSTROPHE, '\'', '"'),
+	("KEY_EQUAL", evdev::Key::KEY_EQUAL, '=', '+'),
+	("KEY_COMMA", evdev::Key::KEY_COMMA, ',', '<'),
+	("KEY_MINUS", evdev::Key::KEY_MINUS, '-', '_'),
+	("KEY_DOT", evdev::Key::KEY_DOT, '.', '>'),
+	("KEY_SLASH", evdev::Key::KEY_SLASH, '/', '?'),
+	("KEY_BACKSLASH", evdev::Key::KEY_BACKSLASH, '\\', '|'),
+	("KEY_SEMICOLON", evdev::Key::KEY_SEMICOLON, ';', ':'),
+	("KEY_LEFTBRACE", evdev::Key::KEY_LEFTBRACE, '[', '{'),
+	("KEY_RIGHTBRACE", evdev::Key::KEY_RIGHTBRACE, ']', '}'),
+	("KEY_GRAVE", evdev::Key::KEY_GRAVE, '`', '~'),
+	("KEY_KPENTER", evdev::Key::KEY_KPENTER, '\n', '\n'),
+	("KEY_ENTER", evdev::Key::KEY_ENTER, '\n', '\n'),
+];
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn us_layout_maps_digits_and_letters_at_both_shift_levels() {
+		let layout = Layout::us();
+		assert_eq!(layout.lookup(evdev::Key::KEY_A, Modifier::Base), Some('a'));
+		assert_eq!(layout.lookup(evdev::Key::KEY_A, Modifier::Shift), Some('A'));
+		assert_eq!(layout.lookup(evdev::Key::KEY_1, Modifier::Shift), Some('!'));
+	}
+
+	#[test]
+	fn shift_falls_back_to_unshifted_when_not_set() {
+		let mut table = HashMap::new();
+		table.insert("KEY_A".to_string(), LayoutEntry { unshifted: Some('a'), shifted: None, altgr: None });
+		let layout = Layout::from_table(table).unwrap();
+		assert_eq!(layout.lookup(evdev::Key::KEY_A, Modifier::Shift), Some('a'));
+	}
+
+	#[test]
+	fn from_table_resolves_altgr() {
+		let mut table = HashMap::new();
+		table.insert("KEY_Q".to_string(), LayoutEntry { unshifted: Some('q'), shifted: Some('Q'), altgr: Some('@') });
+		let layout = Layout::from_table(table).unwrap();
+		assert_eq!(layout.lookup(evdev::Key::KEY_Q, Modifier::AltGr), Some('@'));
+	}
+
+	#[test]
+	fn from_table_rejects_unknown_key_names() {
+		let mut table = HashMap::new();
+		table.insert("KEY_DOES_NOT_EXIST".to_string(), LayoutEntry::default());
+		assert!(Layout::from_table(table).is_err());
+	}
+
+	#[test]
+	fn from_table_accepts_keys_outside_the_us_default() {
+		let mut table = HashMap::new();
+		table.insert("KEY_102ND".to_string(), LayoutEntry { unshifted: Some('<'), shifted: Some('>'), altgr: Some('|') });
+		let layout = Layout::from_table(table).unwrap();
+		assert_eq!(layout.lookup(evdev::Key::KEY_102ND, Modifier::Base), Some('<'));
+	}
+}
@@ -9,6 +9,17 @@
 //! * Prevent other clients from receiving events from the selected device by grabbing it.
 //! * Read 1D barcode consisting of numbers and letters.
 //! * Omit special characters in a barcode.
+//! * Discover and open scanners by their human-readable name via [`BarcodeScanner::list`] and
+//!   [`BarcodeScanner::open_by_name`].
+//! * Survive a scanner being unplugged and re-plugged via [`BarcodeScanner::open_watched`].
+//! * Decode barcodes with a configurable [`Layout`] instead of a hardcoded US QWERTY table.
+//! * Recover from a dropped evdev event buffer (`SYN_DROPPED`) without emitting a corrupt barcode.
+//! * Read barcodes as a cancellation-safe [`futures_core::Stream`] via [`BarcodeScanner::into_stream`],
+//!   with no extra OS thread, behind the `tokio` and `futures` features.
+//! * Service several scanners from one thread with [`ScannerSet`], multiplexed over `epoll`
+//!   instead of busy-waiting or a thread per device.
+//! * Configure barcode [`Framing`]: accepted terminators, prefix/suffix stripping, and an
+//!   idle timeout for scanners that send no terminator at all.
 //!
 //! # Example
 //! This example grabs a hand scanner and prints a barcode that is read.
@@ -25,21 +36,79 @@
 //! # }
 //! ```
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+mod decoder;
+mod framing;
+mod layout;
+mod multi;
+mod watch;
+
+use decoder::Decoder;
+pub use framing::{Framing, Terminator};
+pub use layout::{Layout, LayoutEntry};
+pub use multi::{ScannerId, ScannerSet};
+pub use watch::{ReconnectPolicy, Selector};
+use watch::DeviceWatcher;
+
+#[cfg(all(feature = "tokio", feature = "futures"))]
+mod stream;
+#[cfg(all(feature = "tokio", feature = "futures"))]
+pub use stream::BarcodeStream;
+
+/// Information about an input device discovered via [`BarcodeScanner::list`].
+#[derive(Debug, Clone)]
+pub struct DeviceInfo {
+	/// The human-readable device name, e.g. `"Datalogic ADC, Inc. Handheld Barcode Scanner"`.
+	pub name: String,
+
+	/// The `/dev/input/eventN` path the device can be opened by.
+	pub path: PathBuf,
+
+	/// The physical path of the device, if the kernel reports one.
+	pub physical_path: Option<String>,
+
+	/// Whether the device advertises enough keyboard keys to plausibly be a
+	/// barcode scanner acting as a keyboard.
+	///
+	/// This is a heuristic based on the device supporting [`evdev::Key::KEY_ENTER`]
+	/// plus at least one digit key; it is not a guarantee that the device is a
+	/// scanner.
+	pub is_keyboard_like: bool,
+}
 
 /// A barcode scanner.
 pub struct BarcodeScanner {
-	/// The underlying evdev device.
-	device: evdev::Device,
+	/// The underlying evdev device, or `None` while a watched scanner is disconnected.
+	device: Option<evdev::Device>,
+
+	/// Turns the device's key events into complete barcodes.
+	decoder: Decoder,
+
+	/// How to find this device again after a disconnect, set by [`BarcodeScanner::open_watched`].
+	selector: Option<Selector>,
+
+	/// What to do in `read()` while the device is disconnected.
+	policy: ReconnectPolicy,
 
-	/// A buffer used to collect keystrokes in until a whole barcode has been read.
-	buffer: String,
+	/// A watcher over `/dev/input`, used to notice when a matching device reappears.
+	watcher: Option<DeviceWatcher>,
 }
 
 /// An error reported by the barcode scanner.
 #[derive(Debug, Clone)]
 pub struct Error {
 	msg: String,
+	kind: ErrorKind,
+}
+
+/// What kind of problem an [`Error`] describes. Not exposed as a public `enum`
+/// so new kinds can be added without breaking callers; use [`Error::is_sync_dropped`]
+/// to check for the one kind callers need to distinguish today.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ErrorKind {
+	Io,
+	SyncDropped,
 }
 
 impl BarcodeScanner {
@@ -60,10 +129,7 @@ impl BarcodeScanner {
 		device.grab()
 			.map_err(|e| Error::new(format!("Failed to grab input device {}: {e}", path.display())))?;
 
-		Ok(Self {
-			device,
-			buffer: String::new(),
-		})
+		Ok(Self::connected(device))
 	}
 
 	/// Create a barcode scanner and grab the device by a physical device path
@@ -91,15 +157,163 @@ impl BarcodeScanner {
 				// Prevents other clients from receiving events from this device.
 				device.grab()
 					.map_err(|e| Error::new(format!("Failed to grab input device {physical_path}: {e}")))?;
-				return Ok(Some(Self {
-					device,
-					buffer: String::new(),
-				}))
+				return Ok(Some(Self::connected(device)))
+			}
+		}
+		Ok(None)
+	}
+
+	/// Create a barcode scanner and grab the device by its human-readable name.
+	///
+	/// The name is matched against [`evdev::Device::name`]. If several devices
+	/// share the same name, the first one encountered during enumeration is used.
+	/// Prefer this over [`BarcodeScanner::open`] when the `/dev/input/eventN` path
+	/// is not stable across reboots.
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::BarcodeScanner;
+	/// # fn foo() -> Result<(), ()> {
+	/// let mut scanner = BarcodeScanner::open_by_name("Datalogic ADC, Inc. Handheld Barcode Scanner")
+	///     .map_err(|e| eprintln!("{}", e))?
+	///     .ok_or_else(|| eprintln!("No such device"))?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn open_by_name(name: &str) -> Result<Option<Self>, Error> {
+		for (_path, mut device) in evdev::enumerate() {
+			if device.name() == Some(name) {
+				device.grab()
+					.map_err(|e| Error::new(format!("Failed to grab input device {name}: {e}")))?;
+				return Ok(Some(Self::connected(device)))
 			}
 		}
 		Ok(None)
 	}
 
+	/// Create a barcode scanner that survives the underlying device being unplugged
+	/// and re-plugged.
+	///
+	/// `selector` is used both to find the device initially and to recognize it
+	/// again after a disconnect; `policy` controls what `read()` does while the
+	/// device is gone. Internally this watches `/dev/input` with `inotify` for
+	/// `CREATE`/`DELETE` events; with [`ReconnectPolicy::Block`] it waits on those
+	/// events rather than polling, while [`ReconnectPolicy::RetryWithBackoff`]
+	/// retries on a timed schedule.
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::{BarcodeScanner, ReconnectPolicy, Selector};
+	/// # fn foo() -> Result<(), barcode_scanner::Error> {
+	/// let selector = Selector::Name("Datalogic ADC, Inc. Handheld Barcode Scanner".into());
+	/// let mut scanner = BarcodeScanner::open_watched(selector, ReconnectPolicy::Block)?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn open_watched(selector: Selector, policy: ReconnectPolicy) -> Result<Self, Error> {
+		let watcher = DeviceWatcher::new()?;
+		let device = match selector.resolve() {
+			Some((_path, mut device)) => {
+				device.grab()
+					.map_err(|e| Error::new(format!("Failed to grab input device: {e}")))?;
+				Some(device)
+			}
+			None => None,
+		};
+
+		Ok(Self {
+			device,
+			decoder: Decoder::new(Layout::us()),
+			selector: Some(selector),
+			policy,
+			watcher: Some(watcher),
+		})
+	}
+
+	/// Build a scanner that owns an already-opened and grabbed device, with no
+	/// reconnect behavior configured.
+	fn connected(device: evdev::Device) -> Self {
+		Self {
+			device: Some(device),
+			decoder: Decoder::new(Layout::us()),
+			selector: None,
+			policy: ReconnectPolicy::Error,
+			watcher: None,
+		}
+	}
+
+	/// Decode key events using `layout` instead of the default [`Layout::us`].
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::{BarcodeScanner, Layout};
+	/// # fn foo() -> Result<(), barcode_scanner::Error> {
+	/// let scanner = BarcodeScanner::open("/dev/input/event18")?
+	///     .with_layout(Layout::us());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn with_layout(mut self, layout: Layout) -> Self {
+		*self.decoder.layout() = layout;
+		self
+	}
+
+	/// Split barcodes using `framing` instead of the default (`\n`-terminated,
+	/// no prefix/suffix stripping, no idle timeout).
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::{BarcodeScanner, Framing, Terminator};
+	/// # fn foo() -> Result<(), barcode_scanner::Error> {
+	/// let scanner = BarcodeScanner::open("/dev/input/event18")?
+	///     .with_framing(Framing::default().with_terminator(Terminator::Cr));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn with_framing(mut self, framing: Framing) -> Self {
+		*self.decoder.framing() = framing;
+		self
+	}
+
+	/// List all `evdev` input devices currently visible to this process.
+	///
+	/// Use this to let a user pick a scanner by name rather than guessing a
+	/// `/dev/input/eventN` path. See [`DeviceInfo::is_keyboard_like`] for a hint
+	/// at which of the listed devices are likely to be barcode scanners.
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::BarcodeScanner;
+	/// for device in BarcodeScanner::list() {
+	///     if device.is_keyboard_like {
+	///         println!("{}: {}", device.path.display(), device.name);
+	///     }
+	/// }
+	/// ```
+	pub fn list() -> Vec<DeviceInfo> {
+		evdev::enumerate()
+			.map(|(path, device)| {
+				const DIGIT_KEYS: [evdev::Key; 10] = [
+					evdev::Key::KEY_0, evdev::Key::KEY_1, evdev::Key::KEY_2, evdev::Key::KEY_3,
+					evdev::Key::KEY_4, evdev::Key::KEY_5, evdev::Key::KEY_6, evdev::Key::KEY_7,
+					evdev::Key::KEY_8, evdev::Key::KEY_9,
+				];
+				let is_keyboard_like = device.supported_keys()
+					.is_some_and(|keys| {
+						keys.contains(evdev::Key::KEY_ENTER)
+							&& DIGIT_KEYS.iter().any(|&key| keys.contains(key))
+					});
+
+				DeviceInfo {
+					name: device.name().unwrap_or("").to_string(),
+					path,
+					physical_path: device.physical_path().map(str::to_string),
+					is_keyboard_like,
+				}
+			})
+			.collect()
+	}
+
 	/// Read a barcode from the device.
 	///
 	/// Blocks until an entire barcode has been read.
@@ -115,44 +329,103 @@ impl BarcodeScanner {
 	/// # }
 	pub fn read(&mut self) -> Result<String, Error> {
 		loop {
-			let events = self.device.fetch_events()
-				.map_err(|e| Error::new(format!("Failed to fetch events from input device: {e}")))?;
+			if self.device.is_none() {
+				self.reconnect()?;
+			}
 
-			// Track the state of the shift keys and capslock
-			let mut left_shift_pressed = false;
-			let mut right_shift_pressed = false;
-			let mut capslock_on = false;
+			if let Some(timeout) = self.decoder.idle_timeout() {
+				let device = self.device.as_mut().expect("reconnect() always leaves a device or returns an error");
+				if !wait_readable(device, timeout)? {
+					if let Some(barcode) = self.decoder.flush_idle() {
+						return Ok(barcode);
+					}
+					continue;
+				}
+			}
+
+			let device = self.device.as_mut().expect("reconnect() always leaves a device or returns an error");
+			let events = match fetch_events(device) {
+				FetchOutcome::Events(events) => events,
+				FetchOutcome::Disconnected => {
+					self.device = None;
+					continue;
+				},
+				FetchOutcome::Failed(err) => return Err(err),
+			};
+
+			let mut sync_dropped = false;
+			let mut barcode = None;
 			for event in events {
-				// Check if key is pressed (value 1 for the key pressed, velue 0 for the key released).
-				if event.event_type() == evdev::EventType::KEY {
-					// Create Key object based on the code.
-					let key_name = evdev::Key(event.code());
-
-					match key_name {
-						evdev::Key::KEY_LEFTSHIFT => left_shift_pressed = event.value() == 1,
-						evdev::Key::KEY_RIGHTSHIFT => right_shift_pressed = event.value() == 1,
-						evdev::Key::KEY_CAPSLOCK => capslock_on = event.value() == 1,
-						_ => {},
-					}					
-
-                    // Map key_name to the number or char.
-                    if event.value() == 1 {
-                        if let Some(c) = key_to_str(key_name, left_shift_pressed || right_shift_pressed || capslock_on) {
-                            self.buffer.push(c);
-                        }
-                    }
+				if Decoder::is_sync_dropped(&event) {
+					// The kernel's evdev queue overflowed; everything since the last
+					// SYN_REPORT is suspect. Keep draining this batch, then resync below.
+					sync_dropped = true;
+					continue;
+				}
+				if let Some(result) = self.decoder.feed_event(event) {
+					barcode = Some(result);
+					break;
+				}
+			}
+
+			if sync_dropped {
+				let device = self.device.as_mut().expect("reconnect() always leaves a device or returns an error");
+				if let Some(err) = self.decoder.recover_from_sync_drop(device) {
+					return Err(err);
 				}
+				continue;
 			}
 
-			if let Some(index) = self.buffer.find('\n') {
-				let mut barcode: String= self.buffer.drain(..index + 1).collect();
-				barcode.pop();
+			if let Some(barcode) = barcode {
 				return Ok(barcode);
 			}
 		}
 	}
 
+	/// Re-acquire `self.device` after a disconnect, following `self.policy`.
+	///
+	/// Scanners not opened with [`BarcodeScanner::open_watched`] always have
+	/// [`ReconnectPolicy::Error`], so for them this just returns an [`Error`].
+	fn reconnect(&mut self) -> Result<(), Error> {
+		let max_attempts = match self.policy {
+			ReconnectPolicy::Error => {
+				return Err(Error::new("Input device was disconnected".to_string()));
+			},
+			ReconnectPolicy::Block => None,
+			ReconnectPolicy::RetryWithBackoff { max } => Some(max),
+		};
+
+		let selector = self.selector.as_ref()
+			.expect("only open_watched sets a policy other than ReconnectPolicy::Error");
+		let watcher = self.watcher.as_ref()
+			.expect("open_watched always sets a watcher");
+
+		let mut attempt = 0;
+		loop {
+			if let Some((_path, mut device)) = selector.resolve() {
+				device.grab()
+					.map_err(|e| Error::new(format!("Failed to grab reconnected input device: {e}")))?;
+				self.device = Some(device);
+				return Ok(());
+			}
+
+			if let Some(max) = max_attempts {
+				if attempt >= max {
+					return Err(Error::new("Input device did not reappear before giving up".to_string()));
+				}
+				std::thread::sleep(watch::backoff_delay(attempt));
+				attempt += 1;
+			} else {
+				watcher.wait_for_create()?;
+			}
+		}
+	}
+
 	/// Convert the device into a asynchonous stream of read barcodes.
+	///
+	/// This spawns a blocking task to service the scanner, which pins an OS
+	/// thread for as long as the channel's receiver is alive. Prefer
+	/// [`BarcodeScanner::into_stream`] when the `futures` feature is available.
 	#[cfg(feature = "tokio")]
 	pub fn into_async_stream(mut self) -> tokio::sync::mpsc::UnboundedReceiver<Result<String, Error>> {
 		let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
@@ -165,78 +438,110 @@ impl BarcodeScanner {
 		});
 		rx
 	}
+
+	/// Convert the scanner into a non-blocking stream of decoded barcodes,
+	/// built directly on evdev's own async `EventStream` rather than a
+	/// blocking task, so no extra OS thread is used and dropping the stream
+	/// cancels the read.
+	///
+	/// On a scanner opened with [`BarcodeScanner::open_watched`], reconnect
+	/// behavior isn't carried over to the stream, and an [`Error`] is returned
+	/// if the device is disconnected at the time of the call.
+	///
+	/// # Example
+	/// ```no_run
+	/// # use barcode_scanner::BarcodeScanner;
+	/// # use futures_util::StreamExt;
+	/// # async fn foo() -> Result<(), barcode_scanner::Error> {
+	/// let scanner = BarcodeScanner::open("/dev/input/event18")?;
+	/// let mut stream = scanner.into_stream()?;
+	/// while let Some(barcode) = stream.next().await {
+	///     println!("{}", barcode?);
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	#[cfg(all(feature = "tokio", feature = "futures"))]
+	pub fn into_stream(self) -> Result<BarcodeStream, Error> {
+		let device = self.device
+			.ok_or_else(|| Error::new("Cannot create a stream from a disconnected scanner".to_string()))?;
+		BarcodeStream::new(device, self.decoder)
+	}
+}
+
+/// Whether an I/O error from `fetch_events()` indicates the device node went
+/// away, e.g. the scanner was unplugged, rather than some other failure.
+fn is_disconnect_error(error: &std::io::Error) -> bool {
+	matches!(error.raw_os_error(), Some(libc::ENODEV)) || error.kind() == std::io::ErrorKind::NotFound
+}
+
+/// The outcome of [`fetch_events`], fully owned so matching on it doesn't keep
+/// any borrow of the device alive.
+enum FetchOutcome {
+	/// A batch of events was read.
+	Events(Vec<evdev::InputEvent>),
+	/// The device node went away; `self.device` should be set to `None`.
+	Disconnected,
+	/// Some other I/O failure occurred.
+	Failed(Error),
+}
+
+/// Fetch one batch of events from `device`, classifying the result.
+///
+/// `evdev`'s `fetch_events()` returns an iterator that trims the device's
+/// internal buffer on drop, so it keeps a borrow of `device` alive for as long
+/// as it exists. Collecting it into an owned `Vec` before returning, rather
+/// than inside the caller's `match`, guarantees that borrow ends here instead
+/// of lingering to the end of the caller's enclosing block.
+fn fetch_events(device: &mut evdev::Device) -> FetchOutcome {
+	match device.fetch_events() {
+		Ok(events) => FetchOutcome::Events(events.collect()),
+		Err(e) if is_disconnect_error(&e) => FetchOutcome::Disconnected,
+		Err(e) => FetchOutcome::Failed(Error::new(format!("Failed to fetch events from input device: {e}"))),
+	}
 }
 
-/// Map a scanned key to a character
-fn key_to_str(key: evdev::Key, capital: bool) -> Option<char> {
-    let char = match key {
-        // Digits
-        evdev::Key::KEY_1 => ['1', '!'],
-        evdev::Key::KEY_2 => ['2', '@'],
-        evdev::Key::KEY_3 => ['3', '#'],
-        evdev::Key::KEY_4 => ['4', '$'],
-        evdev::Key::KEY_5 => ['5', '%'],
-        evdev::Key::KEY_6 => ['6', '^'],
-        evdev::Key::KEY_7 => ['7', '&'],
-        evdev::Key::KEY_8 => ['8', '*'],
-        evdev::Key::KEY_9 => ['9', '('],
-        evdev::Key::KEY_0 => ['0', ')'],
-        // Letters
-        evdev::Key::KEY_A => ['a','A'],
-        evdev::Key::KEY_B => ['b','B'],
-        evdev::Key::KEY_C => ['c','C'],
-        evdev::Key::KEY_D => ['d','D'],
-        evdev::Key::KEY_E => ['e','E'],
-        evdev::Key::KEY_F => ['f','F'],
-        evdev::Key::KEY_G => ['g','G'],
-        evdev::Key::KEY_H => ['h','H'],
-        evdev::Key::KEY_I => ['i','I'],
-        evdev::Key::KEY_J => ['j','J'],
-        evdev::Key::KEY_K => ['k','K'],
-        evdev::Key::KEY_L => ['l','L'],
-        evdev::Key::KEY_M => ['m','M'],
-        evdev::Key::KEY_N => ['n','N'],
-        evdev::Key::KEY_O => ['o','O'],
-        evdev::Key::KEY_P => ['p','P'],
-        evdev::Key::KEY_Q => ['q','Q'],
-        evdev::Key::KEY_R => ['r','R'],
-        evdev::Key::KEY_S => ['s','S'],
-        evdev::Key::KEY_T => ['t','T'],
-        evdev::Key::KEY_U => ['u','U'],
-        evdev::Key::KEY_V => ['v','V'],
-        evdev::Key::KEY_W => ['w','W'],
-        evdev::Key::KEY_X => ['x','X'],
-        evdev::Key::KEY_Y => ['y','Y'],
-        evdev::Key::KEY_Z => ['z','Z'],
-        // Special
-        evdev::Key::KEY_SPACE => [' ', ' '],
-        evdev::Key::KEY_TAB => ['\t', '\t'],
-        evdev::Key::KEY_APOSTROPHE => ['\'', '"'],
-        evdev::Key::KEY_EQUAL => ['=', '+'],
-        evdev::Key::KEY_COMMA => [',', '<'],
-        evdev::Key::KEY_MINUS => ['-', '_'],
-        evdev::Key::KEY_DOT => ['.', '>'],
-        evdev::Key::KEY_SLASH => ['/', '?'],
-        evdev::Key::KEY_BACKSLASH => ['\\', '|'],
-        evdev::Key::KEY_SEMICOLON => [';', ':'],
-        evdev::Key::KEY_LEFTBRACE => ['[', '{'],
-        evdev::Key::KEY_RIGHTBRACE => [']', '}'],
-        evdev::Key::KEY_GRAVE => ['`', '~'],
-        evdev::Key::KEY_KPENTER => ['\n', '\n'],
-        evdev::Key::KEY_ENTER => ['\n', '\n'],
-        _ => return None
-    };
-
-    if capital {
-        Some(char[1])
-    } else {
-        Some(char[0])
-    }
+/// Wait up to `timeout` for `device` to have events ready to fetch.
+///
+/// Returns `true` if the device became readable, `false` if `timeout` elapsed
+/// first. Used to implement [`Framing::with_idle_timeout`] without a busy
+/// loop, the same way [`ScannerSet`] waits on several devices at once.
+fn wait_readable(device: &evdev::Device, timeout: std::time::Duration) -> Result<bool, Error> {
+	use std::os::fd::{AsRawFd, BorrowedFd};
+
+	use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+
+	let epoll = Epoll::new(EpollCreateFlags::empty())
+		.map_err(|e| Error::new(format!("Failed to create epoll instance: {e}")))?;
+	// SAFETY: the borrow doesn't outlive this call, and `device` is still alive.
+	let fd = unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) };
+	epoll.add(fd, EpollEvent::new(EpollFlags::EPOLLIN, 0))
+		.map_err(|e| Error::new(format!("Failed to register input device with epoll: {e}")))?;
+
+	let mut events = [EpollEvent::empty(); 1];
+	let timeout = EpollTimeout::try_from(timeout)
+		.map_err(|e| Error::new(format!("Idle timeout out of range for epoll: {e}")))?;
+	let ready = epoll.wait(&mut events, timeout)
+		.map_err(|e| Error::new(format!("Failed to wait on epoll instance: {e}")))?;
+
+	Ok(ready > 0)
 }
 
 impl Error {
 	fn new(msg: String) -> Self {
-		Self { msg }
+		Self { msg, kind: ErrorKind::Io }
+	}
+
+	fn sync_dropped(msg: String) -> Self {
+		Self { msg, kind: ErrorKind::SyncDropped }
+	}
+
+	/// Whether this error means a barcode in progress was discarded because the
+	/// kernel's evdev event buffer overflowed (`SYN_DROPPED`), rather than the
+	/// device failing outright. The scanner has already resynced; simply call
+	/// `read()` again to scan the next barcode.
+	pub fn is_sync_dropped(&self) -> bool {
+		self.kind == ErrorKind::SyncDropped
 	}
 }
 
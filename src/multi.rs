@@ -0,0 +1,130 @@
+//! Servicing several scanners from a single thread with `epoll`.
+
+use std::collections::HashMap;
+use std::os::fd::{AsRawFd, BorrowedFd};
+
+use nix::fcntl::{fcntl, FcntlArg, OFlag};
+use nix::sys::epoll::{Epoll, EpollCreateFlags, EpollEvent, EpollFlags, EpollTimeout};
+
+use crate::decoder::Decoder;
+use crate::{Error, Layout};
+
+/// A stable identifier for a device added to a [`ScannerSet`] with [`ScannerSet::add`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ScannerId(u64);
+
+/// A device owned by a [`ScannerSet`], along with its own decode state.
+struct Entry {
+	device: evdev::Device,
+	decoder: Decoder,
+}
+
+/// Services multiple grabbed scanners from a single thread.
+///
+/// Each device's raw fd is registered with `epoll` and set non-blocking, so
+/// [`ScannerSet::read_any`] can wait on all of them at once without
+/// busy-waiting or a thread per device.
+pub struct ScannerSet {
+	epoll: Epoll,
+	entries: HashMap<ScannerId, Entry>,
+	next_id: u64,
+}
+
+impl ScannerSet {
+	/// Create an empty set.
+	pub fn new() -> Result<Self, Error> {
+		let epoll = Epoll::new(EpollCreateFlags::empty())
+			.map_err(|e| Error::new(format!("Failed to create epoll instance: {e}")))?;
+
+		Ok(Self {
+			epoll,
+			entries: HashMap::new(),
+			next_id: 0,
+		})
+	}
+
+	/// Add an already-opened and grabbed device to the set, switching it to
+	/// non-blocking mode, and return a stable id for it.
+	pub fn add(&mut self, device: evdev::Device) -> Result<ScannerId, Error> {
+		// SAFETY: the borrow doesn't outlive this call, and `device` (which owns
+		// the fd) is still alive.
+		let fd = unsafe { BorrowedFd::borrow_raw(device.as_raw_fd()) };
+
+		fcntl(fd, FcntlArg::F_SETFL(OFlag::O_NONBLOCK))
+			.map_err(|e| Error::new(format!("Failed to set input device non-blocking: {e}")))?;
+
+		let id = ScannerId(self.next_id);
+		self.next_id += 1;
+
+		self.epoll.add(fd, EpollEvent::new(EpollFlags::EPOLLIN, id.0))
+			.map_err(|e| Error::new(format!("Failed to register input device with epoll: {e}")))?;
+
+		self.entries.insert(id, Entry { device, decoder: Decoder::new(Layout::us()) });
+		Ok(id)
+	}
+
+	/// Remove a device from the set, returning it.
+	pub fn remove(&mut self, id: ScannerId) -> Option<evdev::Device> {
+		let entry = self.entries.remove(&id)?;
+		// SAFETY: the borrow doesn't outlive this call, and `entry.device` (which
+		// owns the fd) is still alive.
+		let fd = unsafe { BorrowedFd::borrow_raw(entry.device.as_raw_fd()) };
+		let _ = self.epoll.delete(fd);
+		Some(entry.device)
+	}
+
+	/// Block until any device in the set completes a barcode, returning which
+	/// one scanned it.
+	pub fn read_any(&mut self) -> Result<(ScannerId, String), Error> {
+		loop {
+			let mut events = [EpollEvent::empty(); 16];
+			let ready = self.epoll.wait(&mut events, EpollTimeout::NONE)
+				.map_err(|e| Error::new(format!("Failed to wait on epoll instance: {e}")))?;
+
+			for event in &events[..ready] {
+				let id = ScannerId(event.data());
+				let Some(entry) = self.entries.get_mut(&id) else { continue };
+
+				if let Some(result) = Self::drain(entry, id)? {
+					return Ok(result);
+				}
+			}
+		}
+	}
+
+	/// Drain all events currently available on `entry`'s device without
+	/// blocking, returning a completed barcode if one was assembled.
+	fn drain(entry: &mut Entry, id: ScannerId) -> Result<Option<(ScannerId, String)>, Error> {
+		loop {
+			let batch = match entry.device.fetch_events() {
+				Ok(batch) => batch,
+				Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => return Ok(None),
+				Err(e) => return Err(Error::new(format!("Failed to fetch events from input device: {e}"))),
+			};
+
+			let mut sync_dropped = false;
+			let mut barcode = None;
+			for event in batch {
+				if Decoder::is_sync_dropped(&event) {
+					sync_dropped = true;
+					continue;
+				}
+				if let Some(result) = entry.decoder.feed_event(event) {
+					barcode = Some(result);
+					break;
+				}
+			}
+
+			if sync_dropped {
+				if let Some(err) = entry.decoder.recover_from_sync_drop(&entry.device) {
+					return Err(err);
+				}
+				continue;
+			}
+
+			if let Some(barcode) = barcode {
+				return Ok(Some((id, barcode)));
+			}
+		}
+	}
+}
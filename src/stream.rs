@@ -0,0 +1,91 @@
+//! A non-blocking `futures`/`tokio` stream of decoded barcodes.
+//!
+//! Built directly on evdev's own async `EventStream`, instead of
+//! [`crate::BarcodeScanner::into_async_stream`]'s `spawn_blocking` task, so
+//! reading a scanner no longer pins an OS thread and dropping the stream
+//! cancels the read.
+//!
+//! `EventStream` resyncs a dropped event buffer internally and only ever hands
+//! us synthesized compensation events, never a raw `SYN_DROPPED`; see
+//! [`BarcodeStream`] for what that means for a barcode caught mid-scan.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_core::Stream;
+
+use crate::decoder::Decoder;
+use crate::Error;
+
+/// A stream of decoded barcodes read from a [`crate::BarcodeScanner`].
+///
+/// Construct one with [`crate::BarcodeScanner::into_stream`].
+///
+/// Unlike [`crate::BarcodeScanner::read`], this does not report a dropped
+/// event buffer as an [`Error`]. evdev's own `EventStream` resyncs a
+/// `SYN_DROPPED` internally and replaces it with synthesized key/LED events
+/// that bring decoder state back in line, so the decoder never sees anything
+/// flagged as a drop; a barcode caught mid-scan by one is read as a (possibly
+/// garbled) barcode rather than an error.
+pub struct BarcodeStream {
+	inner: evdev::EventStream,
+	decoder: Decoder,
+
+	/// Fires [`crate::Framing::with_idle_timeout`] after the buffer, reset on
+	/// every event so it always measures time since the *last* keystroke.
+	idle: Option<Pin<Box<tokio::time::Sleep>>>,
+}
+
+impl BarcodeStream {
+	pub(crate) fn new(device: evdev::Device, decoder: Decoder) -> Result<Self, Error> {
+		let inner = device.into_event_stream()
+			.map_err(|e| Error::new(format!("Failed to create async event stream: {e}")))?;
+		let mut stream = Self { inner, decoder, idle: None };
+		stream.reset_idle();
+		Ok(stream)
+	}
+
+	fn reset_idle(&mut self) {
+		if let Some(timeout) = self.decoder.idle_timeout() {
+			self.idle = Some(Box::pin(tokio::time::sleep(timeout)));
+		}
+	}
+}
+
+impl Stream for BarcodeStream {
+	type Item = Result<String, Error>;
+
+	fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+		let this = self.get_mut();
+		loop {
+			if let Some(idle) = this.idle.as_mut() {
+				if idle.as_mut().poll(cx).is_ready() {
+					let flushed = this.decoder.flush_idle();
+					this.reset_idle();
+					if let Some(barcode) = flushed {
+						return Poll::Ready(Some(Ok(barcode)));
+					}
+				}
+			}
+
+			let event = match Pin::new(&mut this.inner).poll_next(cx) {
+				Poll::Ready(Some(Ok(event))) => event,
+				Poll::Ready(Some(Err(e))) => {
+					return Poll::Ready(Some(Err(Error::new(format!("Failed to read from input device: {e}")))));
+				},
+				Poll::Ready(None) => return Poll::Ready(None),
+				Poll::Pending => return Poll::Pending,
+			};
+
+			this.reset_idle();
+
+			// No `Decoder::is_sync_dropped` check here: `EventStream` never
+			// surfaces a raw `SYN_DROPPED`, so it can never fire, see the
+			// module and struct docs above.
+			if let Some(barcode) = this.decoder.feed_event(event) {
+				return Poll::Ready(Some(Ok(barcode)));
+			}
+		}
+	}
+}
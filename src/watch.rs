@@ -0,0 +1,115 @@
+//! Hotplug-aware reconnection support.
+//!
+//! Barcode scanners are USB devices and routinely get unplugged and re-plugged.
+//! This module provides the pieces [`crate::BarcodeScanner::open_watched`] needs
+//! to find a device again after it disappears: a [`Selector`] that describes
+//! *which* device to look for, a [`ReconnectPolicy`] that describes *how* to
+//! react while it's gone, and a small `/dev/input` watcher built on
+//! `nix::sys::inotify`.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+
+use crate::Error;
+
+/// Identifies which device [`crate::BarcodeScanner::open_watched`] should open,
+/// and which device it should look for again after a disconnect.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+	/// Match by [`evdev::Device::name`].
+	Name(String),
+
+	/// Match by [`evdev::Device::physical_path`].
+	PhysicalPath(String),
+
+	/// Match by USB vendor and product id, as reported by [`evdev::Device::input_id`].
+	VendorProduct { vendor: u16, product: u16 },
+}
+
+impl Selector {
+	/// Find the first currently-connected device matching this selector.
+	pub(crate) fn resolve(&self) -> Option<(PathBuf, evdev::Device)> {
+		evdev::enumerate().find(|(_path, device)| self.matches(device))
+	}
+
+	fn matches(&self, device: &evdev::Device) -> bool {
+		match self {
+			Selector::Name(name) => device.name() == Some(name.as_str()),
+			Selector::PhysicalPath(path) => device.physical_path() == Some(path.as_str()),
+			Selector::VendorProduct { vendor, product } => {
+				let id = device.input_id();
+				id.vendor() == *vendor && id.product() == *product
+			}
+		}
+	}
+}
+
+/// What a [`crate::BarcodeScanner`] opened with [`crate::BarcodeScanner::open_watched`]
+/// should do when its underlying device disappears.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReconnectPolicy {
+	/// Return an [`Error`] from `read()` as soon as the device disappears, same as
+	/// a scanner opened with [`crate::BarcodeScanner::open`].
+	Error,
+
+	/// Block inside `read()` until a matching device reappears, then resume
+	/// filling the buffer as if nothing happened.
+	Block,
+
+	/// Retry opening the device with exponentially increasing backoff, up to
+	/// `max` attempts, before giving up and returning an [`Error`].
+	RetryWithBackoff { max: u32 },
+}
+
+/// A watcher over `/dev/input` that reports when device nodes are created or removed.
+pub(crate) struct DeviceWatcher {
+	inotify: Inotify,
+}
+
+impl DeviceWatcher {
+	pub(crate) fn new() -> Result<Self, Error> {
+		let inotify = Inotify::init(InitFlags::empty())
+			.map_err(|e| Error::new(format!("Failed to initialize inotify: {e}")))?;
+		inotify.add_watch(
+			Path::new("/dev/input"),
+			AddWatchFlags::IN_CREATE | AddWatchFlags::IN_DELETE,
+		)
+		.map_err(|e| Error::new(format!("Failed to watch /dev/input: {e}")))?;
+
+		Ok(Self { inotify })
+	}
+
+	/// Block until `/dev/input` reports a `CREATE` event, i.e. a new device node
+	/// may have appeared.
+	pub(crate) fn wait_for_create(&self) -> Result<(), Error> {
+		loop {
+			let events = self.inotify.read_events()
+				.map_err(|e| Error::new(format!("Failed to read inotify events: {e}")))?;
+			if events.iter().any(|event| event.mask.contains(AddWatchFlags::IN_CREATE)) {
+				return Ok(());
+			}
+		}
+	}
+}
+
+/// The backoff schedule used by [`ReconnectPolicy::RetryWithBackoff`]: doubles
+/// the delay after every failed attempt, starting at 100ms and capping at 5s.
+pub(crate) fn backoff_delay(attempt: u32) -> Duration {
+	let millis = 100u64.saturating_mul(1u64 << attempt.min(7));
+	Duration::from_millis(millis.min(5000))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn doubles_until_the_five_second_cap() {
+		assert_eq!(backoff_delay(0), Duration::from_millis(100));
+		assert_eq!(backoff_delay(1), Duration::from_millis(200));
+		assert_eq!(backoff_delay(2), Duration::from_millis(400));
+		assert_eq!(backoff_delay(10), Duration::from_millis(5000));
+	}
+}